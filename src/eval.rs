@@ -0,0 +1,194 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use types::{Binop, Bool, Expr, Lit, Name};
+
+// A runtime value. Functions evaluate to closures that capture the
+// environment in force where the `Lam` was written, so evaluation is
+// lexically scoped and call-by-value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value<'a> {
+    VInt(i64),
+    VBool(Bool),
+    VClosure { param: Name, body: &'a Expr<'a>, env: Env<'a> }
+}
+
+// A persistent, `Rc`-linked chain of bindings. Extending a scope shares the
+// parent frames, and the binding cell is a `RefCell` so `Fix` can backpatch
+// a frame into a self-reference after it has been created.
+#[derive(Debug, PartialEq)]
+pub enum Scope<'a> {
+    Nil,
+    Cons(Name, RefCell<Value<'a>>, Env<'a>)
+}
+
+pub type Env<'a> = Rc<Scope<'a>>;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum EvalError {
+    UnboundVariable(Name),
+    NotAFunction,
+    TypeMismatch
+}
+
+// The empty environment.
+pub fn empty<'a>() -> Env<'a> {
+    Rc::new(Scope::Nil)
+}
+
+// Build a child scope binding `name` to `value` on top of `env`.
+pub fn extend<'a>(env : &Env<'a>, name : Name, value : Value<'a>) -> Env<'a> {
+    Rc::new(Scope::Cons(name, RefCell::new(value), env.clone()))
+}
+
+// Walk the scope chain returning the nearest binding for `name`.
+pub fn lookup<'a>(env : &Env<'a>, name : &str) -> Option<Value<'a>> {
+    match **env {
+        Scope::Nil => None,
+        Scope::Cons(ref n, ref v, ref next) =>
+            if n == name { Some(v.borrow().clone()) } else { lookup(next, name) }
+    }
+}
+
+fn apply_op<'a>(op : &Binop, l : Value<'a>, r : Value<'a>)
+    -> Result<Value<'a>, EvalError> {
+    match (l, r) {
+        (Value::VInt(a), Value::VInt(b)) => match *op {
+            Binop::Add => Ok(Value::VInt(a + b)),
+            Binop::Sub => Ok(Value::VInt(a - b)),
+            Binop::Mul => Ok(Value::VInt(a * b)),
+            Binop::Eql => Ok(Value::VBool(if a == b { Bool::True } else { Bool::False }))
+        },
+        _ => Err(EvalError::TypeMismatch)
+    }
+}
+
+// Evaluate `e` in `env`. Arguments are reduced to values before a function
+// is entered (call-by-value); operations on the wrong shape of value fail
+// with `EvalError` rather than panicking.
+pub fn eval<'a>(e : &'a Expr<'a>, env : Env<'a>) -> Result<Value<'a>, EvalError> {
+    match *e {
+        Expr::Var(ref x) => match lookup(&env, x) {
+            Some(v) => Ok(v),
+            None    => Err(EvalError::UnboundVariable(x.clone()))
+        },
+        Expr::Lit(ref l) => match *l {
+            Lit::LInt(n)      => Ok(Value::VInt(n)),
+            Lit::LBool(ref b) => Ok(Value::VBool(b.clone()))
+        },
+        Expr::Lam(ref x, body) =>
+            Ok(Value::VClosure { param: x.clone(), body: body, env: env.clone() }),
+        Expr::App(f, a) => {
+            let fv = try!(eval(f, env.clone()));
+            let av = try!(eval(a, env));
+            match fv {
+                Value::VClosure { param, body, env: cenv } =>
+                    eval(body, extend(&cenv, param, av)),
+                _ => Err(EvalError::NotAFunction)
+            }
+        },
+        Expr::Let(ref x, e1, e2) => {
+            let v = try!(eval(e1, env.clone()));
+            eval(e2, extend(&env, x.clone(), v))
+        },
+        Expr::If(c, t, f) => match try!(eval(c, env.clone())) {
+            Value::VBool(Bool::True)  => eval(t, env),
+            Value::VBool(Bool::False) => eval(f, env),
+            _ => Err(EvalError::TypeMismatch)
+        },
+        Expr::Op(ref op, l, r) => {
+            let lv = try!(eval(l, env.clone()));
+            let rv = try!(eval(r, env));
+            apply_op(op, lv, rv)
+        },
+        Expr::Fix(e1) => match try!(eval(e1, env)) {
+            Value::VClosure { param, body, env: cenv } => {
+                // Create the recursive frame with a placeholder binding, then
+                // backpatch it to the fixpoint. `body` is the function `Fix`
+                // recurses through, so evaluating it yields a closure that
+                // captures `frame`; tying `param` back to that closure is what
+                // lets `fix (\f n -> … f …)` call itself.
+                //
+                // The backpatch intentionally forms an `Rc` cycle (frame → cell
+                // → closure → frame): the self-reference is the point, and the
+                // frame lives only as long as the recursive closure, so the
+                // leak is bounded. Never derive `PartialEq`-compare two such
+                // closures — the cycle makes the structural comparison diverge.
+                let frame = extend(&cenv, param, Value::VInt(0));
+                let fixed = try!(eval(body, frame.clone()));
+                if let Scope::Cons(_, ref cell, _) = *frame {
+                    *cell.borrow_mut() = fixed.clone();
+                }
+                Ok(fixed)
+            },
+            _ => Err(EvalError::NotAFunction)
+        }
+    }
+}
+
+#[test]
+fn eval_lit_test() {
+    assert!(eval(&Expr::Lit(Lit::LInt(7)), empty()) == Ok(Value::VInt(7)));
+    assert!(eval(&Expr::Lit(Lit::LBool(Bool::True)), empty())
+            == Ok(Value::VBool(Bool::True)));
+}
+
+#[test]
+fn eval_let_test() {
+    // let x = 1 + 2 in x * x
+    let one = Expr::Lit(Lit::LInt(1));
+    let two = Expr::Lit(Lit::LInt(2));
+    let add = Expr::Op(Binop::Add, &one, &two);
+    let x = Expr::Var("x".to_string());
+    let sq = Expr::Op(Binop::Mul, &x, &x);
+    let e = Expr::Let("x".to_string(), &add, &sq);
+    assert!(eval(&e, empty()) == Ok(Value::VInt(9)));
+}
+
+#[test]
+fn eval_app_test() {
+    // (\x -> x + 1) 41
+    let x = Expr::Var("x".to_string());
+    let one = Expr::Lit(Lit::LInt(1));
+    let body = Expr::Op(Binop::Add, &x, &one);
+    let lam = Expr::Lam("x".to_string(), &body);
+    let arg = Expr::Lit(Lit::LInt(41));
+    let app = Expr::App(&lam, &arg);
+    assert!(eval(&app, empty()) == Ok(Value::VInt(42)));
+}
+
+#[test]
+fn eval_if_test() {
+    let t = Expr::Lit(Lit::LBool(Bool::True));
+    let one = Expr::Lit(Lit::LInt(1));
+    let two = Expr::Lit(Lit::LInt(2));
+    let e = Expr::If(&t, &one, &two);
+    assert!(eval(&e, empty()) == Ok(Value::VInt(1)));
+}
+
+#[test]
+fn eval_fix_factorial_test() {
+    // let fact = fix (\f n -> if n == 0 then 1 else n * f (n - 1)) in fact 5
+    let n = Expr::Var("n".to_string());
+    let zero = Expr::Lit(Lit::LInt(0));
+    let one = Expr::Lit(Lit::LInt(1));
+    let cond = Expr::Op(Binop::Eql, &n, &zero);
+    let nm1 = Expr::Op(Binop::Sub, &n, &one);
+    let f = Expr::Var("f".to_string());
+    let call = Expr::App(&f, &nm1);
+    let mul = Expr::Op(Binop::Mul, &n, &call);
+    let body = Expr::If(&cond, &one, &mul);
+    let inner = Expr::Lam("n".to_string(), &body);
+    let outer = Expr::Lam("f".to_string(), &inner);
+    let fixed = Expr::Fix(&outer);
+    let five = Expr::Lit(Lit::LInt(5));
+    let fact = Expr::Var("fact".to_string());
+    let app = Expr::App(&fact, &five);
+    let prog = Expr::Let("fact".to_string(), &fixed, &app);
+    assert!(eval(&prog, empty()) == Ok(Value::VInt(120)));
+}
+
+#[test]
+fn eval_unbound_test() {
+    let e = Expr::Var("nope".to_string());
+    assert!(eval(&e, empty()) == Err(EvalError::UnboundVariable("nope".to_string())));
+}
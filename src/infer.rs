@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use types::*;
+
+// The monomorphic types inferred for `Expr`. Type variables are plain
+// integer ids drawn from a fresh supply; `TCon` names the ground types
+// (`Int`, `Bool`) and `TArrow` is the function space.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    TVar(u32),
+    TCon(&'static str),
+    TArrow(Box<Type>, Box<Type>)
+}
+
+// A type closed over a set of quantified variables, i.e. the `forall` that
+// `let` introduces so a binding can be used at several instantiations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scheme(pub Vec<u32>, pub Type);
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TypeError {
+    UnboundVariable(Name),
+    Mismatch(Type, Type),
+    OccursCheck(u32, Type)
+}
+
+// A substitution maps type variables to types; a typing environment maps
+// program names to their generalized schemes.
+pub type Subst = HashMap<u32, Type>;
+pub type TypeEnv = HashMap<Name, Scheme>;
+
+// A monotonic counter handing out fresh type variables.
+fn fresh(supply : &mut u32) -> Type {
+    let n = *supply;
+    *supply += 1;
+    Type::TVar(n)
+}
+
+// Apply a substitution to a type, chasing variables transitively.
+fn apply(s : &Subst, t : &Type) -> Type {
+    match *t {
+        Type::TVar(n) => match s.get(&n) {
+            Some(bound) => apply(s, bound),
+            None        => Type::TVar(n)
+        },
+        Type::TCon(c) => Type::TCon(c),
+        Type::TArrow(ref a, ref b) =>
+            Type::TArrow(Box::new(apply(s, a)), Box::new(apply(s, b)))
+    }
+}
+
+// Apply a substitution to a scheme, leaving its bound variables untouched.
+fn apply_scheme(s : &Subst, sc : &Scheme) -> Scheme {
+    let mut s = s.clone();
+    for v in &sc.0 {
+        s.remove(v);
+    }
+    Scheme(sc.0.clone(), apply(&s, &sc.1))
+}
+
+fn apply_env(s : &Subst, env : &TypeEnv) -> TypeEnv {
+    let mut out = TypeEnv::new();
+    for (name, sc) in env {
+        out.insert(name.clone(), apply_scheme(s, sc));
+    }
+    out
+}
+
+// `compose(s1, s2)` is the substitution that applies `s2` then `s1`.
+fn compose(s1 : &Subst, s2 : &Subst) -> Subst {
+    let mut out = Subst::new();
+    for (k, v) in s2 {
+        out.insert(*k, apply(s1, v));
+    }
+    for (k, v) in s1 {
+        out.insert(*k, v.clone());
+    }
+    out
+}
+
+fn ftv(t : &Type, acc : &mut HashSet<u32>) {
+    match *t {
+        Type::TVar(n) => { acc.insert(n); },
+        Type::TCon(_) => (),
+        Type::TArrow(ref a, ref b) => { ftv(a, acc); ftv(b, acc); }
+    }
+}
+
+fn ftv_scheme(sc : &Scheme, acc : &mut HashSet<u32>) {
+    let mut inner = HashSet::new();
+    ftv(&sc.1, &mut inner);
+    for v in &sc.0 {
+        inner.remove(v);
+    }
+    for v in inner {
+        acc.insert(v);
+    }
+}
+
+fn ftv_env(env : &TypeEnv) -> HashSet<u32> {
+    let mut acc = HashSet::new();
+    for sc in env.values() {
+        ftv_scheme(sc, &mut acc);
+    }
+    acc
+}
+
+// Bind a type variable to a type, refusing an infinite type via the occurs
+// check and dropping the trivial `TVar(n) = TVar(n)` binding.
+fn bind(n : u32, t : &Type) -> Result<Subst, TypeError> {
+    if let Type::TVar(m) = *t {
+        if m == n {
+            return Ok(Subst::new());
+        }
+    }
+    let mut vars = HashSet::new();
+    ftv(t, &mut vars);
+    if vars.contains(&n) {
+        return Err(TypeError::OccursCheck(n, t.clone()));
+    }
+    let mut s = Subst::new();
+    s.insert(n, t.clone());
+    Ok(s)
+}
+
+// The most general unifier of two types, or a `Mismatch`/`OccursCheck`.
+fn unify(t1 : &Type, t2 : &Type) -> Result<Subst, TypeError> {
+    match (t1, t2) {
+        (&Type::TArrow(ref l1, ref r1), &Type::TArrow(ref l2, ref r2)) => {
+            let s1 = try!(unify(l1, l2));
+            let s2 = try!(unify(&apply(&s1, r1), &apply(&s1, r2)));
+            Ok(compose(&s2, &s1))
+        },
+        (&Type::TVar(n), t) => bind(n, t),
+        (t, &Type::TVar(n)) => bind(n, t),
+        (&Type::TCon(a), &Type::TCon(b)) if a == b => Ok(Subst::new()),
+        _ => Err(TypeError::Mismatch(t1.clone(), t2.clone()))
+    }
+}
+
+// Instantiate a scheme by replacing each quantified variable with a fresh one.
+fn instantiate(sc : &Scheme, supply : &mut u32) -> Type {
+    let mut s = Subst::new();
+    for v in &sc.0 {
+        s.insert(*v, fresh(supply));
+    }
+    apply(&s, &sc.1)
+}
+
+// Generalize a type over the variables free in it but not in the environment.
+fn generalize(env : &TypeEnv, t : &Type) -> Scheme {
+    let mut vars = HashSet::new();
+    ftv(t, &mut vars);
+    for v in ftv_env(env) {
+        vars.remove(&v);
+    }
+    let mut vars: Vec<u32> = vars.into_iter().collect();
+    vars.sort();
+    Scheme(vars, t.clone())
+}
+
+fn lit_type(l : &Lit) -> Type {
+    match *l {
+        Lit::LInt(_)  => Type::TCon("Int"),
+        Lit::LBool(_) => Type::TCon("Bool")
+    }
+}
+
+// Core of Algorithm W: returns the substitution learnt while typing `e`
+// together with the (not yet fully applied) type assigned to it.
+fn algorithm_w(env : &TypeEnv, supply : &mut u32, e : &Expr)
+    -> Result<(Subst, Type), TypeError> {
+    match *e {
+        Expr::Var(ref x) => match env.get(x) {
+            Some(sc) => Ok((Subst::new(), instantiate(sc, supply))),
+            None     => Err(TypeError::UnboundVariable(x.clone()))
+        },
+        Expr::Lit(ref l) => Ok((Subst::new(), lit_type(l))),
+        Expr::Lam(ref x, body) => {
+            let tv = fresh(supply);
+            let mut env1 = env.clone();
+            env1.insert(x.clone(), Scheme(Vec::new(), tv.clone()));
+            let (s1, t1) = try!(algorithm_w(&env1, supply, body));
+            Ok((s1.clone(), Type::TArrow(Box::new(apply(&s1, &tv)), Box::new(t1))))
+        },
+        Expr::App(f, a) => {
+            let (s1, t1) = try!(algorithm_w(env, supply, f));
+            let (s2, t2) = try!(algorithm_w(&apply_env(&s1, env), supply, a));
+            let tv = fresh(supply);
+            let s3 = try!(unify(&apply(&s2, &t1),
+                                &Type::TArrow(Box::new(t2), Box::new(tv.clone()))));
+            Ok((compose(&s3, &compose(&s2, &s1)), apply(&s3, &tv)))
+        },
+        Expr::Let(ref x, e1, e2) => {
+            let (s1, t1) = try!(algorithm_w(env, supply, e1));
+            let env1 = apply_env(&s1, env);
+            let scheme = generalize(&env1, &t1);
+            let mut env2 = env1.clone();
+            env2.insert(x.clone(), scheme);
+            let (s2, t2) = try!(algorithm_w(&env2, supply, e2));
+            Ok((compose(&s2, &s1), t2))
+        },
+        Expr::If(c, t, f) => {
+            let (s1, tc) = try!(algorithm_w(env, supply, c));
+            let s2 = try!(unify(&tc, &Type::TCon("Bool")));
+            let s12 = compose(&s2, &s1);
+            let (s3, tt) = try!(algorithm_w(&apply_env(&s12, env), supply, t));
+            let s123 = compose(&s3, &s12);
+            let (s4, tf) = try!(algorithm_w(&apply_env(&s123, env), supply, f));
+            let s1234 = compose(&s4, &s123);
+            let s5 = try!(unify(&apply(&s1234, &tt), &apply(&s1234, &tf)));
+            let subst = compose(&s5, &s1234);
+            Ok((subst.clone(), apply(&subst, &tf)))
+        },
+        Expr::Fix(e1) => {
+            let (s1, t1) = try!(algorithm_w(env, supply, e1));
+            let tv = fresh(supply);
+            let s2 = try!(unify(&apply(&s1, &t1),
+                                &Type::TArrow(Box::new(tv.clone()), Box::new(tv.clone()))));
+            Ok((compose(&s2, &s1), apply(&s2, &tv)))
+        },
+        Expr::Op(ref op, l, r) => {
+            let (s1, t1) = try!(algorithm_w(env, supply, l));
+            let (s2, t2) = try!(algorithm_w(&apply_env(&s1, env), supply, r));
+            let s12 = compose(&s2, &s1);
+            match *op {
+                Binop::Eql => {
+                    let s3 = try!(unify(&apply(&s12, &t1), &apply(&s12, &t2)));
+                    Ok((compose(&s3, &s12), Type::TCon("Bool")))
+                },
+                _ => {
+                    let s3 = try!(unify(&apply(&s12, &t1), &Type::TCon("Int")));
+                    let s123 = compose(&s3, &s12);
+                    let s4 = try!(unify(&apply(&s123, &t2), &Type::TCon("Int")));
+                    Ok((compose(&s4, &s123), Type::TCon("Int")))
+                }
+            }
+        }
+    }
+}
+
+// Infer the principal type of `e` under `env`, with every substitution
+// learnt along the way applied to the result.
+pub fn infer(env : &TypeEnv, e : &Expr) -> Result<Type, TypeError> {
+    let mut supply = 0;
+    let (s, t) = try!(algorithm_w(env, &mut supply, e));
+    Ok(apply(&s, &t))
+}
+
+#[test]
+fn infer_lit_test() {
+    let env = TypeEnv::new();
+    assert!(infer(&env, &Expr::Lit(Lit::LInt(1))) == Ok(Type::TCon("Int")));
+    assert!(infer(&env, &Expr::Lit(Lit::LBool(Bool::True))) == Ok(Type::TCon("Bool")));
+}
+
+#[test]
+fn infer_identity_test() {
+    let env = TypeEnv::new();
+    let body = Expr::Var("x".to_string());
+    let id = Expr::Lam("x".to_string(), &body);
+    // \x -> x  is  a -> a
+    match infer(&env, &id) {
+        Ok(Type::TArrow(a, b)) => assert!(a == b),
+        other => panic!("expected an arrow type, got {:?}", other)
+    }
+}
+
+#[test]
+fn infer_op_test() {
+    let env = TypeEnv::new();
+    let one = Expr::Lit(Lit::LInt(1));
+    let two = Expr::Lit(Lit::LInt(2));
+    let add = Expr::Op(Binop::Add, &one, &two);
+    assert!(infer(&env, &add) == Ok(Type::TCon("Int")));
+    let eql = Expr::Op(Binop::Eql, &one, &two);
+    assert!(infer(&env, &eql) == Ok(Type::TCon("Bool")));
+}
+
+#[test]
+fn infer_if_test() {
+    let env = TypeEnv::new();
+    let one = Expr::Lit(Lit::LInt(1));
+    let two = Expr::Lit(Lit::LInt(2));
+    let cond = Expr::Lit(Lit::LBool(Bool::True));
+    let e = Expr::If(&cond, &one, &two);
+    assert!(infer(&env, &e) == Ok(Type::TCon("Int")));
+    // A non-boolean condition is a type error.
+    let bad = Expr::If(&one, &one, &two);
+    assert!(infer(&env, &bad).is_err());
+}
+
+#[test]
+fn infer_fix_factorial_test() {
+    // let fact = fix (\f n -> if n == 0 then 1 else n * f (n - 1)) in fact 5
+    let env = TypeEnv::new();
+    let n = Expr::Var("n".to_string());
+    let zero = Expr::Lit(Lit::LInt(0));
+    let one = Expr::Lit(Lit::LInt(1));
+    let cond = Expr::Op(Binop::Eql, &n, &zero);
+    let nm1 = Expr::Op(Binop::Sub, &n, &one);
+    let f = Expr::Var("f".to_string());
+    let call = Expr::App(&f, &nm1);
+    let mul = Expr::Op(Binop::Mul, &n, &call);
+    let body = Expr::If(&cond, &one, &mul);
+    let inner = Expr::Lam("n".to_string(), &body);
+    let outer = Expr::Lam("f".to_string(), &inner);
+    let fixed = Expr::Fix(&outer);
+    let five = Expr::Lit(Lit::LInt(5));
+    let fact = Expr::Var("fact".to_string());
+    let app = Expr::App(&fact, &five);
+    let prog = Expr::Let("fact".to_string(), &fixed, &app);
+    assert!(infer(&env, &prog) == Ok(Type::TCon("Int")));
+}
+
+#[test]
+fn infer_unbound_test() {
+    let env = TypeEnv::new();
+    let e = Expr::Var("nope".to_string());
+    assert!(infer(&env, &e) == Err(TypeError::UnboundVariable("nope".to_string())));
+}
@@ -6,7 +6,7 @@ use types::*;
 // use std::result::*;
 
 #[derive(Debug, PartialEq, Eq)]
-enum ReaderError {
+pub enum ReaderErrorKind {
     EOF,
     CannotUnreadChar,
     InvalidToken,
@@ -15,16 +15,93 @@ enum ReaderError {
     InvalidKeyword
 }
 
+// A half-open source span, tracked in both bytes and the line/column the
+// offending character sits on so diagnostics can point a caret at it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Span {
+    start: usize,
+    end:   usize,
+    line:  usize,
+    col:   usize
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ReaderError {
+    kind: ReaderErrorKind,
+    span: Span
+}
+
 struct Reader<'a> {
-    s:   Peekable<Chars<'a>>,
-    buf: Vec<char>
+    s:      Peekable<Chars<'a>>,
+    buf:    Vec<char>,
+    offset: usize,
+    line:   usize,
+    col:    usize,
+    // The position at which the form currently being read began; `read_form`
+    // sets this before dispatching so an error underlines the whole offending
+    // token rather than a single point past it.
+    mark:     usize,
+    mark_line: usize,
+    mark_col:  usize
 }
 
 type ReaderResult<T> = Result<T, ReaderError>;
 
 fn string_reader(s : &str) -> Reader {
     let s = s.clone();
-    Reader { s:  s.chars().peekable(), buf: Vec::new() }
+    Reader { s: s.chars().peekable(), buf: Vec::new(), offset: 0, line: 1, col: 1,
+             mark: 0, mark_line: 1, mark_col: 1 }
+}
+
+// Remember the reader's current position as the start of the form about to be
+// read, so a later `error` can span from here to wherever reading stopped.
+fn set_mark(r : &mut Reader) {
+    r.mark = r.offset;
+    r.mark_line = r.line;
+    r.mark_col = r.col;
+}
+
+// The span running from the current form's mark to the reader's position,
+// i.e. the run of source consumed while reading the offending token.
+fn here(r : &Reader) -> Span {
+    Span { start: r.mark, end: r.offset.max(r.mark), line: r.mark_line, col: r.mark_col }
+}
+
+fn error<T>(r : &Reader, kind : ReaderErrorKind) -> ReaderResult<T> {
+    Err(ReaderError { kind: kind, span: here(r) })
+}
+
+// Raise an error spanning from an explicit start position to the reader's
+// current offset; used by maps, whose opening mark has since been
+// overwritten by the forms nested inside them.
+fn error_at<T>(r : &Reader, start : (usize, usize, usize), kind : ReaderErrorKind)
+    -> ReaderResult<T> {
+    let span = Span { start: start.0, end: r.offset.max(start.0),
+                      line: start.1, col: start.2 };
+    Err(ReaderError { kind: kind, span: span })
+}
+
+fn advance(r : &mut Reader, c : char) {
+    r.offset += c.len_utf8();
+    if c == '\n' {
+        r.line += 1;
+        r.col = 1;
+    } else {
+        r.col += 1;
+    }
+}
+
+fn retreat(r : &mut Reader, c : char) {
+    r.offset = r.offset.saturating_sub(c.len_utf8());
+    if c == '\n' {
+        // Stepping back over a newline can restore the line, but the previous
+        // line's length isn't tracked, so the column stays at its post-newline
+        // value. The only newlines ever unread are trailing token delimiters,
+        // where the reader immediately re-advances, so this is harmless.
+        r.line = r.line.saturating_sub(1);
+    } else {
+        r.col = r.col.saturating_sub(1);
+    }
 }
 
 fn peek_char(r : &mut Reader) -> ReaderResult<char> {
@@ -33,23 +110,26 @@ fn peek_char(r : &mut Reader) -> ReaderResult<char> {
     } else {
         match r.s.peek() {
             Some(c) => Ok(*c),
-            None => Err(ReaderError::EOF)
+            None => error(r, ReaderErrorKind::EOF)
         }
     }
 }
 
 fn read_char(r : &mut Reader) -> ReaderResult<char> {
-    match r.buf.pop() {
-        Some (c) => Ok(c),
-        None => match r.s.next() {
-            Some(c) => Ok(c),
-            None => Err(ReaderError::EOF)
-        }
+    let c = match r.buf.pop() {
+        Some(c) => Some(c),
+        None    => r.s.next()
+    };
+    match c {
+        Some(c) => { advance(r, c); Ok(c) },
+        None    => error(r, ReaderErrorKind::EOF)
     }
 }
 
 fn unread_char(r : &mut Reader, c : char) -> ReaderResult<()> {
-    Ok(r.buf.push(c))
+    retreat(r, c);
+    r.buf.push(c);
+    Ok(())
 }
 
 fn read_while(r : &mut Reader, p : &Fn(char) -> bool, eof_err : bool) -> ReaderResult<String> {
@@ -62,7 +142,7 @@ fn read_while(r : &mut Reader, p : &Fn(char) -> bool, eof_err : bool) -> ReaderR
             s.push(c)
         }
     };
-    if eof_err { Err(ReaderError::EOF) } else { Ok(s) }
+    if eof_err { error(r, ReaderErrorKind::EOF) } else { Ok(s) }
 }
 
 fn is_macro_terminating(c : char) -> bool {
@@ -80,14 +160,14 @@ fn is_whitespace(c : char) -> bool {
     }
 }
 
-fn escape_char<'a>(c : char) -> ReaderResult<&'a str> {
+fn escape_char<'a>(c : char) -> Result<&'a str, ReaderErrorKind> {
     match c {
         't'  => Ok("\t"),
         'r'  => Ok("\r"),
         'n'  => Ok("\n"),
         '\\' => Ok("\\"),
         '"'  => Ok("\""),
-        _    => Err(ReaderError::InvalidCharacter)
+        _    => Err(ReaderErrorKind::InvalidCharacter)
     }
 }
 
@@ -97,14 +177,17 @@ fn read_string_type(r : &mut Reader, _ : char) -> ReaderResult<String> {
         let c = read_char(r);
         match c {
             Ok('"') => { return Ok(s) },
-            Ok('\\') => try!(read_char(r)
-                             .and_then(escape_char)
-                             .map(|c| s.push_str(c))),
+            Ok('\\') => {
+                let e = try!(read_char(r));
+                match escape_char(e) {
+                    Ok(esc)  => s.push_str(esc),
+                    Err(kind) => return error(r, kind)
+                }
+            },
             Ok(c) => s.push(c),
             Err(e) => return Err(e)
         }
-    };
-    Ok(s)
+    }
 }
 
 fn read_regex(r : &mut Reader, _ : char) -> ReaderResult<Pattern> {
@@ -112,21 +195,21 @@ fn read_regex(r : &mut Reader, _ : char) -> ReaderResult<Pattern> {
 }
 
 fn read_token(r : &mut Reader, initch : char) -> ReaderResult<String> {
-    read_while(r, &|c| is_macro_terminating(c) || is_whitespace(c), false)
+    read_while(r, &|c| is_macro_terminating(c) || is_whitespace(c) || c == ',', false)
         .map(|s| initch.to_string() + &s)
 }
 
 fn parse_symbol(token : String) ->
-    Result<(Option<String>, String), ReaderError> {
+    Result<(Option<String>, String), ReaderErrorKind> {
     if token.is_empty() || token.ends_with(":") || token.starts_with("::") {
-        Err(ReaderError::InvalidSymbol)
+        Err(ReaderErrorKind::InvalidSymbol)
     } else {
         let mut tokens = token.rsplit("/");
         let name = tokens.next().map(str::to_string);
         let ns = tokens.next().map(str::to_string);
         match name {
             Some(name) => Ok((ns, name)),
-            None => Err(ReaderError::InvalidSymbol)
+            None => Err(ReaderErrorKind::InvalidSymbol)
         }
     }
 }
@@ -137,7 +220,7 @@ fn read_symbol(r : &mut Reader, initch : char) -> ReaderResult<Symbol> {
         Ok(s) => match parse_symbol(s) {
             Ok((None,     name)) => Ok(Symbol::SimpleSymbol(name)),
             Ok((Some(ns), name)) => Ok(Symbol::NamespacedSymbol(ns, name)),
-            Err(e)               => Err(e)
+            Err(kind)            => error(r, kind)
         },
         Err(e) => Err(e)
     }
@@ -145,12 +228,15 @@ fn read_symbol(r : &mut Reader, initch : char) -> ReaderResult<Symbol> {
 
 fn read_keyword(r : &mut Reader, _ : char) -> ReaderResult<Keyword> {
     match read_char(r) {
-        Ok(c) if is_whitespace(c) => Err(ReaderError::InvalidToken),
+        Ok(c) if is_whitespace(c) => error(r, ReaderErrorKind::InvalidToken),
         Ok(c) => {
             let token = try!(read_token(r, c));
-            let (ns, name) = try!(parse_symbol(token.clone()));
+            let (ns, name) = match parse_symbol(token.clone()) {
+                Ok(parts) => parts,
+                Err(kind) => return error(r, kind)
+            };
             match token.chars().nth(0) {
-                Some(':') => Err(ReaderError::InvalidKeyword),
+                Some(':') => error(r, ReaderErrorKind::InvalidKeyword),
                 _ => match ns {
                     Some(ns) => Ok(Keyword::NamespacedKeyword(ns, name)),
                     None => Ok(Keyword::SimpleKeyword(name))
@@ -161,6 +247,219 @@ fn read_keyword(r : &mut Reader, _ : char) -> ReaderResult<Keyword> {
     }
 }
 
+fn skip_whitespace(r : &mut Reader) -> ReaderResult<()> {
+    while let Ok(c) = read_char(r) {
+        if c == ';' {
+            try!(read_while(r, &|c| c == '\n', false));
+        } else if !(is_whitespace(c) || c == ',') {
+            try!(unread_char(r, c));
+            return Ok(())
+        }
+    };
+    error(r, ReaderErrorKind::EOF)
+}
+
+fn read_number(r : &mut Reader, initch : char) -> ReaderResult<Value> {
+    let token = try!(read_token(r, initch));
+    let is_float = token.contains('.') || token.contains('e') || token.contains('E');
+    let parsed = if is_float {
+        token.parse::<f64>().map(Value::Float).ok()
+    } else {
+        token.parse::<i64>().map(Value::Int).ok()
+    };
+    match parsed {
+        Some(v) => Ok(v),
+        None    => error(r, ReaderErrorKind::InvalidToken)
+    }
+}
+
+fn read_character(r : &mut Reader) -> ReaderResult<Character> {
+    let initch = try!(read_char(r));
+    let token = try!(read_token(r, initch));
+    match token.as_ref() {
+        "newline" => Ok(Character('\n')),
+        "return"  => Ok(Character('\r')),
+        "tab"     => Ok(Character('\t')),
+        "space"   => Ok(Character(' ')),
+        _ if token.starts_with('u') && token.chars().count() == 5 =>
+            match u32::from_str_radix(&token[1..], 16).ok().and_then(char::from_u32) {
+                Some(c) => Ok(Character(c)),
+                None    => error(r, ReaderErrorKind::InvalidCharacter)
+            },
+        _ if token.chars().count() == 1 =>
+            Ok(Character(token.chars().next().unwrap())),
+        _ => error(r, ReaderErrorKind::InvalidCharacter)
+    }
+}
+
+fn read_delimited(r : &mut Reader, close : char) -> ReaderResult<Vec<Value>> {
+    let mut forms = Vec::new();
+    loop {
+        try!(skip_whitespace(r));
+        if try!(peek_char(r)) == close {
+            try!(read_char(r));
+            return Ok(forms)
+        };
+        forms.push(try!(read_form(r)));
+    }
+}
+
+fn read_map_forms(r : &mut Reader) -> ReaderResult<Vec<(Value, Value)>> {
+    // Capture the opening `{` before the nested forms overwrite the mark, so
+    // an odd element count underlines the whole map rather than its last key.
+    let start = (r.mark, r.mark_line, r.mark_col);
+    let forms = try!(read_delimited(r, '}'));
+    if forms.len() % 2 != 0 {
+        return error_at(r, start, ReaderErrorKind::InvalidToken)
+    };
+    let mut pairs = Vec::with_capacity(forms.len() / 2);
+    let mut it = forms.into_iter();
+    while let (Some(k), Some(v)) = (it.next(), it.next()) {
+        pairs.push((k, v))
+    };
+    Ok(pairs)
+}
+
+fn quote_symbol(name : &str) -> Value {
+    Value::Sym(Symbol::SimpleSymbol(name.to_string()))
+}
+
+fn read_wrapped(r : &mut Reader, name : &str) -> ReaderResult<Value> {
+    let form = try!(read_form(r));
+    Ok(Value::List(vec![quote_symbol(name), form]))
+}
+
+fn read_unquote(r : &mut Reader) -> ReaderResult<Value> {
+    match peek_char(r) {
+        Ok('@') => { try!(read_char(r)); read_wrapped(r, "unquote-splicing") },
+        _       => read_wrapped(r, "unquote")
+    }
+}
+
+fn read_meta(r : &mut Reader) -> ReaderResult<Value> {
+    let meta = try!(read_form(r));
+    let form = try!(read_form(r));
+    Ok(Value::List(vec![quote_symbol("with-meta"), form, meta]))
+}
+
+fn read_dispatch(r : &mut Reader) -> ReaderResult<Value> {
+    match try!(read_char(r)) {
+        '{' => read_delimited(r, '}').map(Value::Set),
+        '"' => {
+            let p = try!(read_regex(r, '"'));
+            try!(read_char(r)); // discard the closing quote left by read_regex
+            Ok(Value::Regex(p))
+        },
+        _   => error(r, ReaderErrorKind::InvalidToken)
+    }
+}
+
+fn read_form(r : &mut Reader) -> ReaderResult<Value> {
+    try!(skip_whitespace(r));
+    set_mark(r);
+    let c = try!(read_char(r));
+    match c {
+        '(' => read_delimited(r, ')').map(Value::List),
+        '[' => read_delimited(r, ']').map(Value::Vector),
+        '{' => read_map_forms(r).map(Value::Map),
+        ')' | ']' | '}' => error(r, ReaderErrorKind::InvalidToken),
+        '#' => read_dispatch(r),
+        '"' => read_string_type(r, c).map(Value::Str),
+        ':' => read_keyword(r, c).map(Value::Kw),
+        '\\' => read_character(r).map(Value::Char),
+        '\'' => read_wrapped(r, "quote"),
+        '`' => read_wrapped(r, "quasiquote"),
+        '~' => read_unquote(r),
+        '@' => read_wrapped(r, "deref"),
+        '^' => read_meta(r),
+        '+' | '-' => {
+            match peek_char(r) {
+                Ok(d) if d.is_digit(10) => read_number(r, c),
+                _                       => read_symbol(r, c).map(collapse_symbol)
+            }
+        },
+        _ if c.is_digit(10) => read_number(r, c),
+        _ => read_symbol(r, c).map(collapse_symbol)
+    }
+}
+
+fn collapse_symbol(sym : Symbol) -> Value {
+    match sym {
+        Symbol::SimpleSymbol(ref s) if s == "true"  => Value::Bool(Bool::True),
+        Symbol::SimpleSymbol(ref s) if s == "false" => Value::Bool(Bool::False),
+        Symbol::SimpleSymbol(ref s) if s == "nil"   => Value::Nil,
+        sym => Value::Sym(sym)
+    }
+}
+
+// Read a single form from a string slice; the convenience entry point used
+// when the caller has a whole source in hand rather than a live `Reader`.
+pub fn read_str(s : &str) -> ReaderResult<Value> {
+    let mut r = string_reader(s);
+    read_form(&mut r)
+}
+
+fn read_all(r : &mut Reader) -> ReaderResult<Vec<Value>> {
+    let mut forms = Vec::new();
+    loop {
+        match skip_whitespace(r) {
+            Ok(())                                            => (),
+            Err(ReaderError { kind: ReaderErrorKind::EOF, .. }) => return Ok(forms),
+            Err(e)                                            => return Err(e)
+        };
+        forms.push(try!(read_form(r)));
+    }
+}
+
+// Render a codespan-style diagnostic: the source line the error sits on,
+// followed by a caret run underlining the offending span. `color` toggles
+// ANSI escapes so the same routine serves both a terminal and a log file.
+fn render_diagnostic(source : &str, err : &ReaderError, color : bool) -> String {
+    let line = source.lines().nth(err.span.line.saturating_sub(1)).unwrap_or("");
+    let pad: String = ::std::iter::repeat(' ').take(err.span.col.saturating_sub(1)).collect();
+    let width = (err.span.end - err.span.start).max(1);
+    let caret: String = ::std::iter::repeat('^').take(width).collect();
+    let (red, reset) = if color { ("\u{1b}[31m", "\u{1b}[0m") } else { ("", "") };
+    format!("{}\n{}{}{} {:?}{}", line, pad, red, caret, err.kind, reset)
+}
+
+#[test]
+fn render_diagnostic_test() {
+    let mut r = string_reader("(a");
+    let err = read_form(&mut r).expect_err("expected EOF");
+    assert!(err.kind == ReaderErrorKind::EOF);
+    let diag = render_diagnostic("(a", &err, false);
+    assert!(diag.lines().next() == Some("(a"));
+    assert!(diag.contains("^"));
+    assert!(render_diagnostic("(a", &err, true).contains("\u{1b}[31m"));
+}
+
+#[test]
+fn diagnostic_span_test() {
+    // An odd-element map underlines the whole construct from its `{`, not a
+    // zero-width caret one column past the closing brace.
+    let src = "{:a 1 :b}";
+    let err = read_form(&mut string_reader(src)).expect_err("expected odd-map error");
+    assert!(err.kind == ReaderErrorKind::InvalidToken);
+    assert!(err.span.start == 0 && err.span.end == src.len());
+    assert!(err.span.col == 1);
+    let diag = render_diagnostic(src, &err, false);
+    assert!(diag.lines().nth(1).unwrap().starts_with("^^^^^^^^^"));
+}
+
+#[test]
+fn position_test() {
+    let mut r = string_reader("ab\ncd");
+    read_char(&mut r).unwrap();
+    read_char(&mut r).unwrap();
+    read_char(&mut r).unwrap();
+    let c = read_char(&mut r).unwrap();
+    assert!(c == 'c');
+    assert!(r.line == 2 && r.col == 2);
+    unread_char(&mut r, 'c').unwrap();
+    assert!(r.col == 1);
+}
+
 #[test]
 fn peek_char_test() {
     let mut r = string_reader("c");
@@ -190,8 +489,8 @@ fn read_while_test() {
     let mut r2 = string_reader("abc");
     match read_while(&mut r2, f, true) {
         Ok(_) => panic!("Shouldn't have succeeded read_while"),
-        Err(ReaderError::EOF) => (),
-        _ => panic!("Should have Err(ReaderError::EOF)")
+        Err(ReaderError { kind: ReaderErrorKind::EOF, .. }) => (),
+        _ => panic!("Should have Err(ReaderErrorKind::EOF)")
     }
 }
 
@@ -202,7 +501,7 @@ fn read_string_type_test() {
     let mut r = string_reader("abc\\\\\"");
     assert!(read_string_type(&mut r, '"') == Ok("abc\\".to_string()));
     let mut r = string_reader("abc");
-    assert!(read_string_type(&mut r, '"') == Err(ReaderError::EOF));
+    assert!(read_string_type(&mut r, '"').map_err(|e| e.kind) == Err(ReaderErrorKind::EOF));
 }
 
 #[test]
@@ -212,7 +511,7 @@ fn read_regex_test() {
     let mut r = string_reader("abc\\\"");
     assert!(read_regex(&mut r, '"') == Ok(Pattern("abc\\".to_string())));
     let mut r = string_reader("abc");
-    assert!(read_regex(&mut r, '"') == Err(ReaderError::EOF));
+    assert!(read_regex(&mut r, '"').map_err(|e| e.kind) == Err(ReaderErrorKind::EOF));
 }
 
 #[test]
@@ -229,7 +528,7 @@ fn read_token_test() {
 fn parse_symbol_test() {
     assert!(parse_symbol("abc".to_string()) == Ok((None, "abc".to_string())));
     assert!(parse_symbol(":a".to_string()) == Ok((None, ":a".to_string())));
-    assert!(parse_symbol(":".to_string()) == Err(ReaderError::InvalidSymbol));
+    assert!(parse_symbol(":".to_string()) == Err(ReaderErrorKind::InvalidSymbol));
     assert!(parse_symbol(":a".to_string()) == Ok((None, ":a".to_string())));
 }
 
@@ -256,12 +555,67 @@ fn read_keyword_test() {
     assert!(key == Keyword::NamespacedKeyword("ns1".to_string(), "abc".to_string()));
     let mut r3 = string_reader(" ");
     match read_keyword(&mut r3, ':') {
-        Err(err) => assert!(err == ReaderError::InvalidToken),
-        _ => panic!("Should have ReaderError::InvalidToken")
+        Err(err) => assert!(err.kind == ReaderErrorKind::InvalidToken),
+        _ => panic!("Should have ReaderErrorKind::InvalidToken")
     }
     let mut r3 = string_reader(":a ");
     match read_keyword(&mut r3, ':') {
-        Err(err) => assert!(err == ReaderError::InvalidKeyword),
-        _ => panic!("Should have ReaderError::InvalidKeyword")
+        Err(err) => assert!(err.kind == ReaderErrorKind::InvalidKeyword),
+        _ => panic!("Should have ReaderErrorKind::InvalidKeyword")
     }
 }
+
+#[test]
+fn read_form_test() {
+    let mut r = string_reader("[1 :a true nil]");
+    let form = read_form(&mut r).expect("Failed read_form");
+    assert!(form == Value::Vector(vec![
+        Value::Int(1),
+        Value::Kw(Keyword::SimpleKeyword("a".to_string())),
+        Value::Bool(Bool::True),
+        Value::Nil
+    ]));
+    let mut r2 = string_reader("(a, b)");
+    assert!(read_form(&mut r2) == Ok(Value::List(vec![
+        Value::Sym(Symbol::SimpleSymbol("a".to_string())),
+        Value::Sym(Symbol::SimpleSymbol("b".to_string()))
+    ])));
+    let mut r3 = string_reader("{:a 1}");
+    assert!(read_form(&mut r3) == Ok(Value::Map(vec![
+        (Value::Kw(Keyword::SimpleKeyword("a".to_string())), Value::Int(1))
+    ])));
+    let mut r4 = string_reader("{:a}");
+    assert!(read_form(&mut r4).map_err(|e| e.kind) == Err(ReaderErrorKind::InvalidToken));
+}
+
+#[test]
+fn read_form_macro_test() {
+    let mut r = string_reader("'x");
+    assert!(read_form(&mut r) == Ok(Value::List(vec![
+        Value::Sym(Symbol::SimpleSymbol("quote".to_string())),
+        Value::Sym(Symbol::SimpleSymbol("x".to_string()))
+    ])));
+    let mut r2 = string_reader("~@xs");
+    assert!(read_form(&mut r2) == Ok(Value::List(vec![
+        Value::Sym(Symbol::SimpleSymbol("unquote-splicing".to_string())),
+        Value::Sym(Symbol::SimpleSymbol("xs".to_string()))
+    ])));
+}
+
+#[test]
+fn read_character_test() {
+    let mut r = string_reader("newline ");
+    assert!(read_character(&mut r) == Ok(Character('\n')));
+    let mut r2 = string_reader("u0041 ");
+    assert!(read_character(&mut r2) == Ok(Character('A')));
+    let mut r3 = string_reader("a ");
+    assert!(read_character(&mut r3) == Ok(Character('a')));
+}
+
+#[test]
+fn read_all_test() {
+    let mut r = string_reader("1 2 3");
+    assert!(read_all(&mut r) == Ok(vec![Value::Int(1), Value::Int(2), Value::Int(3)]));
+    let mut r2 = string_reader("  ");
+    assert!(read_all(&mut r2) == Ok(vec![]));
+}
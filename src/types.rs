@@ -1,4 +1,4 @@
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Bool { True, False }
 
 pub type Name = String;
@@ -28,3 +28,41 @@ pub enum Binop { Add, Sub, Mul, Eql }
 pub enum Program<'a> { Program(&'a [Decl<'a>], &'a Expr<'a>) }
 
 pub type Decl<'a> = (String, Expr<'a>);
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Symbol {
+  SimpleSymbol(String),
+  NamespacedSymbol(String, String)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Keyword {
+  SimpleKeyword(String),
+  NamespacedKeyword(String, String)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Pattern(pub String);
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Character(pub char);
+
+// A fully parsed EDN value. Unlike the leaf types above this is the
+// recursive product of `read_form`, so collections nest arbitrarily and a
+// reader macro such as `'x` shows up as an ordinary `(quote x)` list.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+  Nil,
+  Bool(Bool),
+  Int(i64),
+  Float(f64),
+  Str(String),
+  Char(Character),
+  Sym(Symbol),
+  Kw(Keyword),
+  Regex(Pattern),
+  List(Vec<Value>),
+  Vector(Vec<Value>),
+  Set(Vec<Value>),
+  Map(Vec<(Value, Value)>)
+}